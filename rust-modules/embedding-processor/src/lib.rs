@@ -3,6 +3,93 @@ use pyo3::exceptions::PyValueError;
 use rayon::prelude::*;
 use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
 
+/// Distance/similarity metric used to rank or compare vectors. `Cosine` and `DotProduct`
+/// are similarities (higher is more similar); `Euclidean` and `Manhattan` are distances
+/// (lower is more similar).
+///
+/// Mirrored in `vector-search/src/lib.rs` (that crate additionally persists the metric
+/// to disk via `code`/`from_code_byte`) — the two extension modules are built
+/// independently, but `parse`/`compute`/`sort_best_first` must stay in sync by hand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Metric {
+    Cosine,
+    Euclidean,
+    DotProduct,
+    Manhattan,
+}
+
+/// Accepts either the metric's name (e.g. `"cosine"`) or its numeric code
+/// (0=Cosine, 1=Euclidean, 2=DotProduct, 3=Manhattan) from Python
+#[derive(FromPyObject)]
+enum MetricArg {
+    Name(String),
+    Code(i64),
+}
+
+impl Metric {
+    fn parse(arg: MetricArg) -> PyResult<Self> {
+        match arg {
+            MetricArg::Name(name) => match name.to_lowercase().as_str() {
+                "cosine" => Ok(Metric::Cosine),
+                "euclidean" | "l2" => Ok(Metric::Euclidean),
+                "dot_product" | "dotproduct" | "inner_product" | "dot" | "ip" => {
+                    Ok(Metric::DotProduct)
+                }
+                "manhattan" | "l1" => Ok(Metric::Manhattan),
+                other => Err(PyValueError::new_err(format!(
+                    "Unknown metric '{}'. Expected one of: cosine, euclidean, dot_product, manhattan",
+                    other
+                ))),
+            },
+            MetricArg::Code(code) => match code {
+                0 => Ok(Metric::Cosine),
+                1 => Ok(Metric::Euclidean),
+                2 => Ok(Metric::DotProduct),
+                3 => Ok(Metric::Manhattan),
+                other => Err(PyValueError::new_err(format!(
+                    "Unknown metric code {}. Expected 0=cosine, 1=euclidean, 2=dot_product, 3=manhattan",
+                    other
+                ))),
+            },
+        }
+    }
+
+    fn higher_is_better(&self) -> bool {
+        matches!(self, Metric::Cosine | Metric::DotProduct)
+    }
+
+    fn compute(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            Metric::Cosine => {
+                let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+                let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+                let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+                if norm_a == 0.0 || norm_b == 0.0 {
+                    0.0
+                } else {
+                    dot / (norm_a * norm_b)
+                }
+            }
+            Metric::Euclidean => a
+                .iter()
+                .zip(b.iter())
+                .map(|(x, y)| (x - y).powi(2))
+                .sum::<f32>()
+                .sqrt(),
+            Metric::DotProduct => a.iter().zip(b.iter()).map(|(x, y)| x * y).sum(),
+            Metric::Manhattan => a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).sum(),
+        }
+    }
+
+    fn sort_best_first(&self, scored: &mut [(usize, f32)]) {
+        if self.higher_is_better() {
+            scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        } else {
+            scored.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        }
+    }
+}
+
 /// Compute cosine similarity between two vectors
 /// Much faster than Python/NumPy for batch operations
 #[pyfunction]
@@ -91,10 +178,15 @@ fn batch_normalize(vectors: Vec<Vec<f32>>) -> PyResult<Vec<Vec<f32>>> {
     Ok(normalized)
 }
 
-/// Compute pairwise distance matrix for a set of vectors
-/// Uses parallel processing for efficiency
+/// Compute pairwise distance/similarity matrix for a set of vectors using the given
+/// metric (defaults to Euclidean). Uses parallel processing for efficiency
 #[pyfunction]
-fn pairwise_distances(vectors: Vec<Vec<f32>>) -> PyResult<Vec<Vec<f32>>> {
+fn pairwise_distances(vectors: Vec<Vec<f32>>, metric: Option<MetricArg>) -> PyResult<Vec<Vec<f32>>> {
+    let metric = match metric {
+        Some(m) => Metric::parse(m)?,
+        None => Metric::Euclidean,
+    };
+
     let n = vectors.len();
     let mut distances = vec![vec![0.0; n]; n];
 
@@ -107,31 +199,24 @@ fn pairwise_distances(vectors: Vec<Vec<f32>>) -> PyResult<Vec<Vec<f32>>> {
                 .map(move |j| {
                     let vec_i = &vectors[i];
                     let vec_j = &vectors[j];
-                    let dist = euclidean_distance_internal(vec_i, vec_j);
-                    (i, j, dist)
+                    let score = metric.compute(vec_i, vec_j);
+                    (i, j, score)
                 })
         })
         .collect();
 
     // Fill the matrix
-    for (i, j, dist) in pairs {
-        distances[i][j] = dist;
-        distances[j][i] = dist; // Symmetric
+    for (i, j, score) in pairs {
+        distances[i][j] = score;
+        distances[j][i] = score; // Symmetric
+    }
+    for i in 0..n {
+        distances[i][i] = metric.compute(&vectors[i], &vectors[i]);
     }
 
     Ok(distances)
 }
 
-/// Internal function to compute Euclidean distance
-fn euclidean_distance_internal(vec_a: &[f32], vec_b: &[f32]) -> f32 {
-    vec_a
-        .iter()
-        .zip(vec_b.iter())
-        .map(|(a, b)| (a - b).powi(2))
-        .sum::<f32>()
-        .sqrt()
-}
-
 /// Compute mean pooling of embeddings (useful for sentence embeddings)
 #[pyfunction]
 fn mean_pooling(embeddings: Vec<Vec<f32>>) -> PyResult<Vec<f32>> {
@@ -163,17 +248,20 @@ fn top_k_similar(
     query: Vec<f32>,
     targets: Vec<Vec<f32>>,
     k: usize,
+    metric: Option<MetricArg>,
 ) -> PyResult<(Vec<usize>, Vec<f32>)> {
-    let similarities = batch_cosine_similarity(query, targets)?;
+    let metric = match metric {
+        Some(m) => Metric::parse(m)?,
+        None => Metric::Cosine,
+    };
 
-    let mut indexed_scores: Vec<(usize, f32)> = similarities
-        .iter()
+    let mut indexed_scores: Vec<(usize, f32)> = targets
+        .par_iter()
         .enumerate()
-        .map(|(i, &score)| (i, score))
+        .map(|(i, target)| (i, metric.compute(&query, target)))
         .collect();
 
-    // Partial sort to get top-k
-    indexed_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    metric.sort_best_first(&mut indexed_scores);
     indexed_scores.truncate(k.min(indexed_scores.len()));
 
     let indices: Vec<usize> = indexed_scores.iter().map(|(i, _)| *i).collect();
@@ -225,4 +313,46 @@ mod tests {
         let mean = mean_pooling(embeddings).unwrap();
         assert_eq!(mean, vec![4.0, 5.0, 6.0]);
     }
+
+    #[test]
+    fn test_pairwise_distances_default_euclidean() {
+        let vectors = vec![vec![0.0, 0.0], vec![3.0, 4.0], vec![0.0, 4.0]];
+        let distances = pairwise_distances(vectors, None).unwrap();
+        assert!((distances[0][1] - 5.0).abs() < 1e-6);
+        assert!((distances[0][2] - 4.0).abs() < 1e-6);
+        assert_eq!(distances[0][0], 0.0);
+        assert_eq!(distances[1][2], distances[2][1]); // symmetric
+    }
+
+    #[test]
+    fn test_pairwise_distances_dot_product_by_code() {
+        let vectors = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![2.0, 0.0]];
+        let distances =
+            pairwise_distances(vectors, Some(MetricArg::Code(2))).unwrap();
+        assert_eq!(distances[0][1], 0.0);
+        assert_eq!(distances[0][2], 2.0);
+    }
+
+    #[test]
+    fn test_top_k_similar_default_cosine() {
+        let query = vec![1.0, 0.0];
+        let targets = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![-1.0, 0.0]];
+        let (indices, scores) = top_k_similar(query, targets, 2, None).unwrap();
+        assert_eq!(indices[0], 0); // identical direction ranks first
+        assert!(scores[0] > scores[1]);
+    }
+
+    #[test]
+    fn test_top_k_similar_manhattan_by_name() {
+        let query = vec![0.0, 0.0];
+        let targets = vec![vec![1.0, 1.0], vec![5.0, 5.0], vec![0.0, 1.0]];
+        let (indices, _) = top_k_similar(
+            query,
+            targets,
+            1,
+            Some(MetricArg::Name("manhattan".to_string())),
+        )
+        .unwrap();
+        assert_eq!(indices[0], 2); // smallest L1 distance, lower is better
+    }
 }
@@ -1,7 +1,270 @@
 use pyo3::prelude::*;
 use pyo3::exceptions::PyValueError;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use memmap2::Mmap;
+use rand::Rng;
+
+/// On-disk format: 4-byte magic, u32 version, u64 dimension, u8 metric code,
+/// u8 normalize flag, 6 bytes padding (keeps the vector block 4-byte aligned),
+/// u64 vector count
+const VECTOR_INDEX_MAGIC: &[u8; 4] = b"VIDX";
+const VECTOR_INDEX_FORMAT_VERSION: u32 = 1;
+const VECTOR_INDEX_HEADER_SIZE: usize = 32;
+
+/// Distance/similarity metric used to rank vectors. `Cosine` and `DotProduct` are
+/// similarities (higher is more similar); `Euclidean` and `Manhattan` are distances
+/// (lower is more similar).
+///
+/// Mirrored in `embedding-processor/src/lib.rs` (that crate has no persisted on-disk
+/// format, so it skips `code`/`from_code_byte`) — the two extension modules are built
+/// independently, but `parse`/`compute`/`sort_best_first` must stay in sync by hand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Metric {
+    Cosine,
+    Euclidean,
+    DotProduct,
+    Manhattan,
+}
+
+/// Accepts either the metric's name (e.g. `"cosine"`) or its numeric code
+/// (0=Cosine, 1=Euclidean, 2=DotProduct, 3=Manhattan) from Python
+#[derive(FromPyObject)]
+enum MetricArg {
+    Name(String),
+    Code(i64),
+}
+
+impl Metric {
+    fn parse(arg: MetricArg) -> PyResult<Self> {
+        match arg {
+            MetricArg::Name(name) => match name.to_lowercase().as_str() {
+                "cosine" => Ok(Metric::Cosine),
+                "euclidean" | "l2" => Ok(Metric::Euclidean),
+                "dot_product" | "dotproduct" | "inner_product" | "dot" | "ip" => {
+                    Ok(Metric::DotProduct)
+                }
+                "manhattan" | "l1" => Ok(Metric::Manhattan),
+                other => Err(PyValueError::new_err(format!(
+                    "Unknown metric '{}'. Expected one of: cosine, euclidean, dot_product, manhattan",
+                    other
+                ))),
+            },
+            MetricArg::Code(code) => match code {
+                0 => Ok(Metric::Cosine),
+                1 => Ok(Metric::Euclidean),
+                2 => Ok(Metric::DotProduct),
+                3 => Ok(Metric::Manhattan),
+                other => Err(PyValueError::new_err(format!(
+                    "Unknown metric code {}. Expected 0=cosine, 1=euclidean, 2=dot_product, 3=manhattan",
+                    other
+                ))),
+            },
+        }
+    }
+
+    fn higher_is_better(&self) -> bool {
+        matches!(self, Metric::Cosine | Metric::DotProduct)
+    }
+
+    /// Stable numeric code used to persist the metric in a `VectorIndex` save file
+    fn code(&self) -> u8 {
+        match self {
+            Metric::Cosine => 0,
+            Metric::Euclidean => 1,
+            Metric::DotProduct => 2,
+            Metric::Manhattan => 3,
+        }
+    }
+
+    fn from_code_byte(code: u8) -> PyResult<Self> {
+        match code {
+            0 => Ok(Metric::Cosine),
+            1 => Ok(Metric::Euclidean),
+            2 => Ok(Metric::DotProduct),
+            3 => Ok(Metric::Manhattan),
+            other => Err(PyValueError::new_err(format!(
+                "Unknown metric code {} in file",
+                other
+            ))),
+        }
+    }
+
+    /// Compute the per-pair kernel for this metric
+    fn compute(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            Metric::Cosine => {
+                let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+                let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+                let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+                if norm_a == 0.0 || norm_b == 0.0 {
+                    0.0
+                } else {
+                    dot / (norm_a * norm_b)
+                }
+            }
+            Metric::Euclidean => a
+                .iter()
+                .zip(b.iter())
+                .map(|(x, y)| (x - y).powi(2))
+                .sum::<f32>()
+                .sqrt(),
+            Metric::DotProduct => a.iter().zip(b.iter()).map(|(x, y)| x * y).sum(),
+            Metric::Manhattan => a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).sum(),
+        }
+    }
+
+    /// Sort `scored` best-first according to this metric's ordering direction
+    fn sort_best_first(&self, scored: &mut [(usize, f32)]) {
+        if self.higher_is_better() {
+            scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        } else {
+            scored.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        }
+    }
+}
+
+/// Keyword-ranking input to `hybrid_search`: either indices already ordered best-first,
+/// or `(index, score)` pairs that get sorted by score (descending) to establish rank order.
+#[derive(FromPyObject)]
+enum KeywordRanking {
+    Indices(Vec<usize>),
+    Scored(Vec<(usize, f32)>),
+}
+
+impl KeywordRanking {
+    /// Returns indices ordered best-first (rank 0 = best match)
+    fn into_ranked_indices(self) -> Vec<usize> {
+        match self {
+            KeywordRanking::Indices(indices) => indices,
+            KeywordRanking::Scored(mut scored) => {
+                scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+                scored.into_iter().map(|(idx, _)| idx).collect()
+            }
+        }
+    }
+}
+
+/// Normalize a vector to unit length (L2 normalization), mirroring
+/// `embedding_processor::normalize_vector`. Returns the vector unchanged if it has zero norm.
+fn normalize_vector(v: &[f32]) -> Vec<f32> {
+    let norm: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        v.to_vec()
+    } else {
+        v.iter().map(|x| x / norm).collect()
+    }
+}
+
+/// A single entry in a `QuantileSummary`, in the classic Greenwald-Khanna `(v, g, delta)`
+/// form: `g` is the number of ranks this tuple accounts for that no earlier tuple already
+/// covers, and `delta` is this tuple's own fixed rank uncertainty, set once when the tuple
+/// is created and never widened by later merges (only `g` accumulates on merge). The
+/// tuple's absolute rank bounds `rmin`/`rmax` are derived by summing `g` over every tuple
+/// up to and including it: `rmin = sum(g)`, `rmax = rmin + delta`. Deriving them instead of
+/// storing them directly is what keeps merges correct — folding a tuple's `g` into its
+/// neighbor cannot silently stretch that neighbor's own uncertainty band.
+#[derive(Clone, Copy, Debug)]
+struct QuantileTuple {
+    value: f32,
+    g: u64,
+    delta: u64,
+}
+
+/// Streaming epsilon-approximate quantile summary (Greenwald-Khanna / Zhang-Wang style).
+/// Keeps a sorted list of `QuantileTuple`s rather than every observation, merging adjacent
+/// tuples whenever doing so keeps the merged tuple's rank uncertainty within
+/// `floor(2*epsilon*n)`, which bounds memory at `O((1/epsilon) log(epsilon*n))` instead
+/// of the `O(n)` a naive "collect everything and sort" approach would need.
+struct QuantileSummary {
+    epsilon: f32,
+    n: u64,
+    tuples: Vec<QuantileTuple>,
+}
+
+impl QuantileSummary {
+    fn new(epsilon: f32) -> Self {
+        QuantileSummary {
+            epsilon: epsilon.max(f32::EPSILON),
+            n: 0,
+            tuples: Vec::new(),
+        }
+    }
+
+    /// Insert a new observation, then compress the summary
+    fn update(&mut self, value: f32) {
+        let pos = self.tuples.partition_point(|t| t.value < value);
+
+        // New minimum/maximum observations have no rank uncertainty; everything else
+        // gets the current worst-case band width. `g` starts at 1 (this tuple accounts
+        // for exactly the one rank it was just inserted at) and only grows via merges
+        let delta = if pos == 0 || pos == self.tuples.len() {
+            0
+        } else {
+            (2.0 * self.epsilon * self.n as f32).floor() as u64
+        };
+
+        self.tuples.insert(pos, QuantileTuple { value, g: 1, delta });
+        self.n += 1;
+
+        self.compress();
+    }
+
+    /// Single backward sweep merging tuple `i-1` into tuple `i` wherever
+    /// `g(i-1) + g(i) + delta(i) <= floor(2*epsilon*n)`. Merging only ever folds one
+    /// tuple's `g` into its surviving neighbor's `g`, leaving that neighbor's own
+    /// `delta` untouched, so repeated merges can't understate the band's true width.
+    /// Tuple 0 is never absorbed, so the exact minimum observation always survives
+    /// (the maximum is symmetrically safe: being the last tuple, it can only ever be
+    /// a merge's survivor, never its victim)
+    fn compress(&mut self) {
+        let threshold = (2.0 * self.epsilon * self.n as f32).floor() as u64;
+        let mut i = self.tuples.len();
+        while i > 2 {
+            i -= 1;
+            let band = self.tuples[i - 1].g + self.tuples[i].g + self.tuples[i].delta;
+            if band <= threshold {
+                let absorbed_g = self.tuples[i - 1].g;
+                self.tuples[i].g += absorbed_g;
+                self.tuples.remove(i - 1);
+            }
+        }
+    }
+
+    /// Return the approximate value at quantile `phi` (`0.0..=1.0`): the value of the
+    /// tuple whose derived rank bounds come closest to bracketing `phi * n`
+    fn query(&self, phi: f32) -> Option<f32> {
+        if self.tuples.is_empty() {
+            return None;
+        }
+        let target_rank = ((phi * self.n as f32).round() as u64).clamp(1, self.n);
+
+        let mut rmin: u64 = 0;
+        let mut best: Option<(u64, f32)> = None;
+        for t in &self.tuples {
+            rmin += t.g;
+            let rmax = rmin + t.delta;
+            let distance = if target_rank < rmin {
+                rmin - target_rank
+            } else if target_rank > rmax {
+                target_rank - rmax
+            } else {
+                0
+            };
+            if best.map_or(true, |(best_distance, _)| distance < best_distance) {
+                best = Some((distance, t.value));
+            }
+            if distance == 0 {
+                break;
+            }
+        }
+
+        best.map(|(_, value)| value)
+    }
+}
 
 /// A fast in-memory vector search index using brute-force search
 /// Optimized with Rust's parallel processing
@@ -10,17 +273,40 @@ struct VectorIndex {
     vectors: Vec<Vec<f32>>,
     metadata: Vec<HashMap<String, String>>,
     dimension: usize,
+    metric: Metric,
+    normalize: bool,
+    // Pre-normalized copy of `vectors`, kept in lockstep, so cosine search is a pure
+    // dot product with no per-query norm recomputation over the whole index. Only
+    // populated when `normalize` is set and `metric` is Cosine.
+    normalized_vectors: Option<Vec<Vec<f32>>>,
+    // Tombstones set by `remove`/`remove_batch`; checked by `search`/`search_with_filter`
+    // so a removed vector's old index stays stable until the next `compact()`
+    deleted: Vec<bool>,
 }
 
 #[pymethods]
 impl VectorIndex {
     #[new]
-    fn new(dimension: usize) -> Self {
-        VectorIndex {
+    fn new(dimension: usize, metric: Option<MetricArg>, normalize: Option<bool>) -> PyResult<Self> {
+        let metric = match metric {
+            Some(m) => Metric::parse(m)?,
+            None => Metric::Cosine,
+        };
+        let normalize = normalize.unwrap_or(true);
+        let normalized_vectors = if normalize && metric == Metric::Cosine {
+            Some(Vec::new())
+        } else {
+            None
+        };
+        Ok(VectorIndex {
             vectors: Vec::new(),
             metadata: Vec::new(),
             dimension,
-        }
+            metric,
+            normalize,
+            normalized_vectors,
+            deleted: Vec::new(),
+        })
     }
 
     /// Add a vector to the index with optional metadata
@@ -33,11 +319,23 @@ impl VectorIndex {
             )));
         }
 
+        if let Some(normalized) = self.normalized_vectors.as_mut() {
+            normalized.push(normalize_vector(&vector));
+        }
         self.vectors.push(vector);
         self.metadata.push(metadata.unwrap_or_default());
+        self.deleted.push(false);
         Ok(self.vectors.len() - 1)
     }
 
+    /// Get the original (non-normalized) vector at a given index
+    fn get_vector(&self, index: usize) -> PyResult<Vec<f32>> {
+        self.vectors
+            .get(index)
+            .cloned()
+            .ok_or_else(|| PyValueError::new_err("Index out of bounds"))
+    }
+
     /// Add multiple vectors in batch (much faster)
     fn add_batch(
         &mut self,
@@ -60,6 +358,9 @@ impl VectorIndex {
 
         // All validated, now add them
         for (i, vector) in vectors.into_iter().enumerate() {
+            if let Some(normalized) = self.normalized_vectors.as_mut() {
+                normalized.push(normalize_vector(&vector));
+            }
             self.vectors.push(vector);
             if let Some(ref meta) = metadata {
                 if i < meta.len() {
@@ -70,13 +371,63 @@ impl VectorIndex {
             } else {
                 self.metadata.push(HashMap::new());
             }
+            self.deleted.push(false);
             indices.push(start_idx + i);
         }
 
         Ok(indices)
     }
 
-    /// Search for k nearest neighbors using cosine similarity
+    /// Tombstone the vector at `index` so it is skipped by `search`/`search_with_filter`
+    /// without shifting any other index. Call `compact()` to physically reclaim the space
+    fn remove(&mut self, index: usize) -> PyResult<()> {
+        let deleted = self
+            .deleted
+            .get_mut(index)
+            .ok_or_else(|| PyValueError::new_err("Index out of bounds"))?;
+        *deleted = true;
+        Ok(())
+    }
+
+    /// Tombstone multiple vectors at once; see `remove`
+    fn remove_batch(&mut self, indices: Vec<usize>) -> PyResult<()> {
+        for index in indices {
+            self.remove(index)?;
+        }
+        Ok(())
+    }
+
+    /// Physically drop every tombstoned row, compacting `vectors`/`metadata` (and the
+    /// pre-normalized cache, if any) in place. Returns an old-index -> new-index remap
+    /// for every surviving row so callers can update their own external ID mapping;
+    /// removed rows are absent from it.
+    fn compact(&mut self) -> HashMap<usize, usize> {
+        let mut remap = HashMap::with_capacity(self.vectors.len());
+        let mut new_vectors = Vec::new();
+        let mut new_metadata = Vec::new();
+        let mut new_normalized = self.normalized_vectors.as_ref().map(|_| Vec::new());
+
+        for old_idx in 0..self.vectors.len() {
+            if self.deleted[old_idx] {
+                continue;
+            }
+            remap.insert(old_idx, new_vectors.len());
+            new_vectors.push(self.vectors[old_idx].clone());
+            new_metadata.push(self.metadata[old_idx].clone());
+            if let Some(normalized) = &self.normalized_vectors {
+                new_normalized.as_mut().unwrap().push(normalized[old_idx].clone());
+            }
+        }
+
+        self.deleted = vec![false; new_vectors.len()];
+        self.vectors = new_vectors;
+        self.metadata = new_metadata;
+        self.normalized_vectors = new_normalized;
+
+        remap
+    }
+
+    /// Search for k nearest neighbors using this index's configured metric
     /// Returns (indices, scores)
     fn search(&self, query: Vec<f32>, k: usize) -> PyResult<(Vec<usize>, Vec<f32>)> {
         if query.len() != self.dimension {
@@ -91,37 +442,50 @@ impl VectorIndex {
             return Ok((Vec::new(), Vec::new()));
         }
 
-        let query_norm: f32 = query.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if query_norm == 0.0 {
-            return Err(PyValueError::new_err("Query vector has zero norm"));
-        }
-
-        // Compute similarities in parallel
-        let mut similarities: Vec<(usize, f32)> = self
-            .vectors
-            .par_iter()
-            .enumerate()
-            .map(|(idx, target)| {
-                let dot_product: f32 = query.iter().zip(target.iter()).map(|(q, t)| q * t).sum();
-                let target_norm: f32 = target.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let mut scored: Vec<(usize, f32)> = if let Some(normalized) = &self.normalized_vectors {
+            let query_norm: f32 = query.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if query_norm == 0.0 {
+                return Err(PyValueError::new_err("Query vector has zero norm"));
+            }
 
-                let similarity = if target_norm == 0.0 {
-                    0.0
-                } else {
-                    dot_product / (query_norm * target_norm)
-                };
+            // Targets are already unit-length, so this is a pure dot product per pair
+            normalized
+                .par_iter()
+                .enumerate()
+                .filter_map(|(idx, target)| {
+                    if self.deleted[idx] {
+                        return None;
+                    }
+                    let dot: f32 = query.iter().zip(target.iter()).map(|(q, t)| q * t).sum();
+                    Some((idx, dot / query_norm))
+                })
+                .collect()
+        } else {
+            if self.metric == Metric::Cosine {
+                let query_norm: f32 = query.iter().map(|x| x * x).sum::<f32>().sqrt();
+                if query_norm == 0.0 {
+                    return Err(PyValueError::new_err("Query vector has zero norm"));
+                }
+            }
 
-                (idx, similarity)
-            })
-            .collect();
+            self.vectors
+                .par_iter()
+                .enumerate()
+                .filter_map(|(idx, target)| {
+                    if self.deleted[idx] {
+                        return None;
+                    }
+                    Some((idx, self.metric.compute(&query, target)))
+                })
+                .collect()
+        };
 
-        // Sort by similarity (descending)
-        similarities.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        self.metric.sort_best_first(&mut scored);
 
         // Take top-k
-        let k = k.min(similarities.len());
-        let indices: Vec<usize> = similarities[..k].iter().map(|(idx, _)| *idx).collect();
-        let scores: Vec<f32> = similarities[..k].iter().map(|(_, score)| *score).collect();
+        let k = k.min(scored.len());
+        let indices: Vec<usize> = scored[..k].iter().map(|(idx, _)| *idx).collect();
+        let scores: Vec<f32> = scored[..k].iter().map(|(_, score)| *score).collect();
 
         Ok((indices, scores))
     }
@@ -134,15 +498,20 @@ impl VectorIndex {
         Ok(self.metadata[index].clone())
     }
 
-    /// Get the number of vectors in the index
+    /// Get the number of vectors in the index, including tombstoned ones not yet
+    /// reclaimed by `compact()`
     fn size(&self) -> usize {
         self.vectors.len()
     }
 
-    /// Clear all vectors and metadata
+    /// Clear all vectors, metadata, and tombstones
     fn clear(&mut self) {
         self.vectors.clear();
         self.metadata.clear();
+        self.deleted.clear();
+        if let Some(normalized) = self.normalized_vectors.as_mut() {
+            normalized.clear();
+        }
     }
 
     /// Search with filters on metadata
@@ -161,63 +530,784 @@ impl VectorIndex {
             )));
         }
 
-        let query_norm: f32 = query.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if query_norm == 0.0 {
-            return Err(PyValueError::new_err("Query vector has zero norm"));
-        }
+        let matches_filter = |idx: usize| -> bool {
+            if self.deleted[idx] {
+                return false;
+            }
+            self.metadata
+                .get(idx)
+                .and_then(|m| m.get(&filter_key))
+                .map(|v| v == &filter_value)
+                .unwrap_or(false)
+        };
 
-        // Filter and compute similarities in parallel
-        let mut similarities: Vec<(usize, f32)> = self
-            .vectors
-            .par_iter()
-            .enumerate()
-            .filter_map(|(idx, target)| {
-                // Check if metadata matches filter
-                if let Some(meta_value) = self.metadata.get(idx).and_then(|m| m.get(&filter_key)) {
-                    if meta_value != &filter_value {
+        let mut scored: Vec<(usize, f32)> = if let Some(normalized) = &self.normalized_vectors {
+            let query_norm: f32 = query.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if query_norm == 0.0 {
+                return Err(PyValueError::new_err("Query vector has zero norm"));
+            }
+
+            normalized
+                .par_iter()
+                .enumerate()
+                .filter_map(|(idx, target)| {
+                    if !matches_filter(idx) {
                         return None;
                     }
-                } else {
-                    return None;
+                    let dot: f32 = query.iter().zip(target.iter()).map(|(q, t)| q * t).sum();
+                    Some((idx, dot / query_norm))
+                })
+                .collect()
+        } else {
+            if self.metric == Metric::Cosine {
+                let query_norm: f32 = query.iter().map(|x| x * x).sum::<f32>().sqrt();
+                if query_norm == 0.0 {
+                    return Err(PyValueError::new_err("Query vector has zero norm"));
                 }
+            }
 
-                let dot_product: f32 = query.iter().zip(target.iter()).map(|(q, t)| q * t).sum();
-                let target_norm: f32 = target.iter().map(|x| x * x).sum::<f32>().sqrt();
+            self.vectors
+                .par_iter()
+                .enumerate()
+                .filter_map(|(idx, target)| {
+                    if !matches_filter(idx) {
+                        return None;
+                    }
+                    Some((idx, self.metric.compute(&query, target)))
+                })
+                .collect()
+        };
 
-                let similarity = if target_norm == 0.0 {
-                    0.0
-                } else {
-                    dot_product / (query_norm * target_norm)
-                };
+        self.metric.sort_best_first(&mut scored);
 
-                Some((idx, similarity))
-            })
+        // Take top-k
+        let k = k.min(scored.len());
+        let indices: Vec<usize> = scored[..k].iter().map(|(idx, _)| *idx).collect();
+        let scores: Vec<f32> = scored[..k].iter().map(|(_, score)| *score).collect();
+
+        Ok((indices, scores))
+    }
+
+    /// Fuse this index's vector search with an externally-ranked keyword list via
+    /// Reciprocal Rank Fusion: `score(d) = sum over lists of weight / (kconst + rank_d)`,
+    /// where `rank_d` is the document's 1-based rank in that list (absent = no contribution).
+    /// `keyword_ranking` is the caller's lexical ranking, best match first, either as plain
+    /// indices or `(index, score)` pairs. Returns the top-k by fused score, best first.
+    fn hybrid_search(
+        &self,
+        query: Vec<f32>,
+        keyword_ranking: KeywordRanking,
+        k: usize,
+        vector_weight: Option<f32>,
+        keyword_weight: Option<f32>,
+        kconst: Option<f32>,
+    ) -> PyResult<(Vec<usize>, Vec<f32>)> {
+        if query.len() != self.dimension {
+            return Err(PyValueError::new_err(format!(
+                "Query dimension mismatch. Expected {}, got {}",
+                self.dimension,
+                query.len()
+            )));
+        }
+
+        let kconst = kconst.unwrap_or(60.0);
+        let vector_weight = vector_weight.unwrap_or(1.0);
+        let keyword_weight = keyword_weight.unwrap_or(1.0);
+
+        let (vector_ranked, _) = self.search(query, self.vectors.len())?;
+        let keyword_ranked = keyword_ranking.into_ranked_indices();
+
+        let mut fused: HashMap<usize, f32> = HashMap::new();
+        for (rank, &idx) in vector_ranked.iter().enumerate() {
+            *fused.entry(idx).or_insert(0.0) += vector_weight / (kconst + (rank + 1) as f32);
+        }
+        for (rank, &idx) in keyword_ranked.iter().enumerate() {
+            if idx >= self.deleted.len() {
+                return Err(PyValueError::new_err("keyword_ranking index out of bounds"));
+            }
+            if self.deleted[idx] {
+                continue;
+            }
+            *fused.entry(idx).or_insert(0.0) += keyword_weight / (kconst + (rank + 1) as f32);
+        }
+
+        let mut scored: Vec<(usize, f32)> = fused.into_iter().collect();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+        let k = k.min(scored.len());
+        let indices: Vec<usize> = scored[..k].iter().map(|(idx, _)| *idx).collect();
+        let scores: Vec<f32> = scored[..k].iter().map(|(_, score)| *score).collect();
+
+        Ok((indices, scores))
+    }
+
+    /// Approximate score distribution over the whole index against `query`, at each
+    /// requested quantile (each in `0.0..=1.0`). Streams every score through a bounded
+    /// `QuantileSummary` instead of collecting and sorting all scores, so memory stays
+    /// small even over a very large index. `epsilon` is the summary's approximation
+    /// error and defaults to 0.01.
+    fn score_quantiles(
+        &self,
+        query: Vec<f32>,
+        quantiles: Vec<f32>,
+        epsilon: Option<f32>,
+    ) -> PyResult<Vec<f32>> {
+        if query.len() != self.dimension {
+            return Err(PyValueError::new_err(format!(
+                "Query dimension mismatch. Expected {}, got {}",
+                self.dimension,
+                query.len()
+            )));
+        }
+        if self.vectors.is_empty() {
+            return Err(PyValueError::new_err("Index is empty"));
+        }
+
+        let mut summary = QuantileSummary::new(epsilon.unwrap_or(0.01));
+
+        if let Some(normalized) = &self.normalized_vectors {
+            let query_norm: f32 = query.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if query_norm == 0.0 {
+                return Err(PyValueError::new_err("Query vector has zero norm"));
+            }
+            for (idx, target) in normalized.iter().enumerate() {
+                if self.deleted[idx] {
+                    continue;
+                }
+                let dot: f32 = query.iter().zip(target.iter()).map(|(q, t)| q * t).sum();
+                summary.update(dot / query_norm);
+            }
+        } else {
+            for (idx, target) in self.vectors.iter().enumerate() {
+                if self.deleted[idx] {
+                    continue;
+                }
+                summary.update(self.metric.compute(&query, target));
+            }
+        }
+
+        Ok(quantiles
+            .into_iter()
+            .map(|phi| summary.query(phi).unwrap_or(f32::NAN))
+            .collect())
+    }
+
+    /// Serialize the index to `path`: a fixed header followed by the vectors laid out
+    /// contiguously (so `load` can memory-map the file) and then the per-row metadata.
+    /// Tombstoned rows left behind by `remove`/`remove_batch` are dropped rather than
+    /// written out, so saving implicitly compacts the on-disk copy.
+    fn save(&self, path: String) -> PyResult<()> {
+        let file = File::create(&path)
+            .map_err(|e| PyValueError::new_err(format!("Failed to create '{}': {}", path, e)))?;
+        let mut writer = BufWriter::new(file);
+
+        let live_count = self.vectors.len() - self.deleted.iter().filter(|d| **d).count();
+
+        writer.write_all(VECTOR_INDEX_MAGIC).map_err(io_err)?;
+        writer
+            .write_all(&VECTOR_INDEX_FORMAT_VERSION.to_le_bytes())
+            .map_err(io_err)?;
+        writer
+            .write_all(&(self.dimension as u64).to_le_bytes())
+            .map_err(io_err)?;
+        writer.write_all(&[self.metric.code()]).map_err(io_err)?;
+        writer
+            .write_all(&[self.normalize as u8])
+            .map_err(io_err)?;
+        writer.write_all(&[0u8; 6]).map_err(io_err)?; // padding, keeps the vector block 4-byte aligned
+        writer
+            .write_all(&(live_count as u64).to_le_bytes())
+            .map_err(io_err)?;
+
+        for (idx, vector) in self.vectors.iter().enumerate() {
+            if self.deleted[idx] {
+                continue;
+            }
+            for value in vector {
+                writer.write_all(&value.to_le_bytes()).map_err(io_err)?;
+            }
+        }
+
+        for (idx, meta) in self.metadata.iter().enumerate() {
+            if self.deleted[idx] {
+                continue;
+            }
+            writer
+                .write_all(&(meta.len() as u32).to_le_bytes())
+                .map_err(io_err)?;
+            for (key, value) in meta {
+                writer
+                    .write_all(&(key.len() as u32).to_le_bytes())
+                    .map_err(io_err)?;
+                writer.write_all(key.as_bytes()).map_err(io_err)?;
+                writer
+                    .write_all(&(value.len() as u32).to_le_bytes())
+                    .map_err(io_err)?;
+                writer.write_all(value.as_bytes()).map_err(io_err)?;
+            }
+        }
+
+        writer.flush().map_err(io_err)?;
+        Ok(())
+    }
+
+    /// Load an index previously written by `save`. The file is memory-mapped so the OS
+    /// pages data in directly instead of buffering a full `read()` copy, and the vector
+    /// block is decoded straight out of the mapping for a read-only reconstruction.
+    #[staticmethod]
+    fn load(path: String) -> PyResult<Self> {
+        let file = File::open(&path)
+            .map_err(|e| PyValueError::new_err(format!("Failed to open '{}': {}", path, e)))?;
+        let mmap = unsafe {
+            Mmap::map(&file)
+                .map_err(|e| PyValueError::new_err(format!("Failed to mmap '{}': {}", path, e)))?
+        };
+
+        if mmap.len() < VECTOR_INDEX_HEADER_SIZE || &mmap[0..4] != VECTOR_INDEX_MAGIC {
+            return Err(PyValueError::new_err("Not a valid VectorIndex file"));
+        }
+
+        let version = u32::from_le_bytes(mmap[4..8].try_into().unwrap());
+        if version != VECTOR_INDEX_FORMAT_VERSION {
+            return Err(PyValueError::new_err(format!(
+                "Unsupported VectorIndex file version {} (expected {})",
+                version, VECTOR_INDEX_FORMAT_VERSION
+            )));
+        }
+
+        let dimension = u64::from_le_bytes(mmap[8..16].try_into().unwrap()) as usize;
+        let metric = Metric::from_code_byte(mmap[16])?;
+        let normalize = mmap[17] != 0;
+        let count = u64::from_le_bytes(mmap[24..32].try_into().unwrap()) as usize;
+
+        let vector_block_len = count
+            .checked_mul(dimension)
+            .and_then(|n| n.checked_mul(4))
+            .ok_or_else(|| PyValueError::new_err("VectorIndex file header overflow"))?;
+        let vectors_start = VECTOR_INDEX_HEADER_SIZE;
+        let vectors_end = vectors_start + vector_block_len;
+        if mmap.len() < vectors_end {
+            return Err(PyValueError::new_err(
+                "VectorIndex file is truncated, or its dimension/count header is inconsistent",
+            ));
+        }
+
+        let mut vectors = Vec::with_capacity(count);
+        for i in 0..count {
+            let row_start = vectors_start + i * dimension * 4;
+            let mut vector = Vec::with_capacity(dimension);
+            for j in 0..dimension {
+                let offset = row_start + j * 4;
+                vector.push(f32::from_le_bytes(mmap[offset..offset + 4].try_into().unwrap()));
+            }
+            vectors.push(vector);
+        }
+
+        let mut offset = vectors_end;
+        let mut metadata = Vec::with_capacity(count);
+        for _ in 0..count {
+            let num_entries = u32::from_le_bytes(read_exact(&mmap, offset, 4)?.try_into().unwrap());
+            offset += 4;
+
+            let mut map = HashMap::new();
+            for _ in 0..num_entries {
+                let key_len =
+                    u32::from_le_bytes(read_exact(&mmap, offset, 4)?.try_into().unwrap()) as usize;
+                offset += 4;
+                let key = String::from_utf8(read_exact(&mmap, offset, key_len)?.to_vec())
+                    .map_err(|e| PyValueError::new_err(e.to_string()))?;
+                offset += key_len;
+
+                let value_len =
+                    u32::from_le_bytes(read_exact(&mmap, offset, 4)?.try_into().unwrap()) as usize;
+                offset += 4;
+                let value = String::from_utf8(read_exact(&mmap, offset, value_len)?.to_vec())
+                    .map_err(|e| PyValueError::new_err(e.to_string()))?;
+                offset += value_len;
+
+                map.insert(key, value);
+            }
+            metadata.push(map);
+        }
+
+        let normalized_vectors = if normalize && metric == Metric::Cosine {
+            Some(vectors.iter().map(|v| normalize_vector(v)).collect())
+        } else {
+            None
+        };
+        let deleted = vec![false; vectors.len()];
+
+        Ok(VectorIndex {
+            vectors,
+            metadata,
+            dimension,
+            metric,
+            normalize,
+            normalized_vectors,
+            deleted,
+        })
+    }
+}
+
+fn io_err(e: std::io::Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn read_exact(mmap: &Mmap, offset: usize, len: usize) -> PyResult<&[u8]> {
+    mmap.get(offset..offset + len)
+        .ok_or_else(|| PyValueError::new_err("VectorIndex metadata block is truncated"))
+}
+
+/// A candidate entry in the HNSW beam search, ordered by distance (closer is "smaller")
+#[derive(Clone, Copy)]
+struct HeapItem(f32, usize);
+
+impl PartialEq for HeapItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for HeapItem {}
+
+impl PartialOrd for HeapItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Approximate nearest-neighbor index using Hierarchical Navigable Small World graphs
+/// Trades a small amount of recall for sub-linear query time on large indices
+#[pyclass]
+struct HnswIndex {
+    vectors: Vec<Vec<f32>>,
+    metadata: Vec<HashMap<String, String>>,
+    dimension: usize,
+    m: usize,
+    m_max0: usize,
+    ef_construction: usize,
+    ml: f64,
+    entry_point: Option<usize>,
+    top_level: i64,
+    // layers[level][node] = neighbor indices for `node` at that level
+    layers: Vec<HashMap<usize, Vec<usize>>>,
+}
+
+impl HnswIndex {
+    fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+        let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+        if norm_a == 0.0 || norm_b == 0.0 {
+            return 1.0;
+        }
+
+        1.0 - (dot / (norm_a * norm_b))
+    }
+
+    fn random_level(&self) -> usize {
+        let mut rng = rand::thread_rng();
+        let u: f64 = rng.gen_range(f64::MIN_POSITIVE..=1.0);
+        (-u.ln() * self.ml).floor() as usize
+    }
+
+    /// Beam search within a single layer, returning up to `ef` nearest candidates to `query`
+    /// sorted closest-first
+    fn search_layer_vec(
+        &self,
+        query: &[f32],
+        entry_points: Vec<usize>,
+        ef: usize,
+        level: usize,
+    ) -> Vec<(usize, f32)> {
+        let mut visited: HashSet<usize> = entry_points.iter().cloned().collect();
+        let mut candidates: BinaryHeap<Reverse<HeapItem>> = BinaryHeap::new();
+        let mut results: BinaryHeap<HeapItem> = BinaryHeap::new();
+
+        for ep in entry_points {
+            let d = Self::cosine_distance(query, &self.vectors[ep]);
+            candidates.push(Reverse(HeapItem(d, ep)));
+            results.push(HeapItem(d, ep));
+        }
+
+        while let Some(Reverse(HeapItem(cand_dist, cand_idx))) = candidates.pop() {
+            let farthest = results.peek().map(|h| h.0).unwrap_or(f32::INFINITY);
+            if cand_dist > farthest && results.len() >= ef {
+                break;
+            }
+
+            if let Some(neighbors) = self.layers.get(level).and_then(|l| l.get(&cand_idx)) {
+                for &n in neighbors {
+                    if visited.insert(n) {
+                        let d = Self::cosine_distance(query, &self.vectors[n]);
+                        let farthest = results.peek().map(|h| h.0).unwrap_or(f32::INFINITY);
+                        if results.len() < ef || d < farthest {
+                            candidates.push(Reverse(HeapItem(d, n)));
+                            results.push(HeapItem(d, n));
+                            if results.len() > ef {
+                                results.pop();
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut out: Vec<(usize, f32)> = results.into_iter().map(|h| (h.1, h.0)).collect();
+        out.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        out
+    }
+
+    /// Distance-based pruning heuristic: keep a candidate only if it is closer to the base
+    /// node (whose distance each entry of `candidates` carries) than to every neighbor
+    /// already selected
+    fn select_neighbors_heuristic(
+        vectors: &[Vec<f32>],
+        candidates: Vec<(usize, f32)>,
+        m: usize,
+    ) -> Vec<usize> {
+        let mut candidates = candidates;
+        candidates.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        let mut selected: Vec<usize> = Vec::new();
+        for (cand_idx, cand_dist) in candidates {
+            if selected.len() >= m {
+                break;
+            }
+            let keep = selected
+                .iter()
+                .all(|&sel| Self::cosine_distance(&vectors[cand_idx], &vectors[sel]) >= cand_dist);
+            if keep {
+                selected.push(cand_idx);
+            }
+        }
+        selected
+    }
+
+    fn prune_neighbors(&mut self, node: usize, level: usize, max_conn: usize) {
+        let neighbors = match self.layers[level].get(&node) {
+            Some(n) if n.len() > max_conn => n.clone(),
+            _ => return,
+        };
+
+        let node_vec = self.vectors[node].clone();
+        let scored: Vec<(usize, f32)> = neighbors
+            .iter()
+            .map(|&n| (n, Self::cosine_distance(&node_vec, &self.vectors[n])))
             .collect();
+        let pruned = Self::select_neighbors_heuristic(&self.vectors, scored, max_conn);
+        self.layers[level].insert(node, pruned);
+    }
 
-        // Sort by similarity (descending)
-        similarities.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    fn insert_node(&mut self, idx: usize) {
+        let level = self.random_level();
+        while self.layers.len() <= level {
+            self.layers.push(HashMap::new());
+        }
+        for l in &mut self.layers[..=level] {
+            l.entry(idx).or_insert_with(Vec::new);
+        }
 
-        // Take top-k
-        let k = k.min(similarities.len());
-        let indices: Vec<usize> = similarities[..k].iter().map(|(idx, _)| *idx).collect();
-        let scores: Vec<f32> = similarities[..k].iter().map(|(_, score)| *score).collect();
+        let entry = match self.entry_point {
+            None => {
+                self.entry_point = Some(idx);
+                self.top_level = level as i64;
+                return;
+            }
+            Some(e) => e,
+        };
+
+        let query = self.vectors[idx].clone();
+        let mut current = entry;
+        let mut current_dist = Self::cosine_distance(&query, &self.vectors[current]);
+
+        let mut lvl = self.top_level;
+        while lvl > level as i64 {
+            loop {
+                let mut changed = false;
+                if let Some(neighbors) = self.layers[lvl as usize].get(&current).cloned() {
+                    for n in neighbors {
+                        let d = Self::cosine_distance(&query, &self.vectors[n]);
+                        if d < current_dist {
+                            current_dist = d;
+                            current = n;
+                            changed = true;
+                        }
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+            lvl -= 1;
+        }
+
+        let start_level = level.min(self.top_level.max(0) as usize);
+        for lvl in (0..=start_level).rev() {
+            let candidates = self.search_layer_vec(&query, vec![current], self.ef_construction, lvl);
+            let max_conn = if lvl == 0 { self.m_max0 } else { self.m };
+            let neighbors = Self::select_neighbors_heuristic(&self.vectors, candidates, self.m);
+
+            for &n in &neighbors {
+                self.layers[lvl].entry(idx).or_default().push(n);
+                self.layers[lvl].entry(n).or_default().push(idx);
+                self.prune_neighbors(n, lvl, max_conn);
+            }
+
+            if let Some(&closest) = neighbors.first() {
+                current = closest;
+            }
+        }
+
+        if level as i64 > self.top_level {
+            self.top_level = level as i64;
+            self.entry_point = Some(idx);
+        }
+    }
+}
+
+#[pymethods]
+impl HnswIndex {
+    /// Create a new HNSW index. `m` caps the number of edges per node on each upper
+    /// layer, with layer 0 allowing `2*m` (default 16, must be >= 2) and `ef_construction` controls the insertion-time
+    /// beam width (default 200); both trade build time and memory for recall.
+    #[new]
+    fn new(dimension: usize, m: Option<usize>, ef_construction: Option<usize>) -> PyResult<Self> {
+        let m = m.unwrap_or(16);
+        if m < 2 {
+            // m=1 drives ml = 1.0/(1.0).ln() = +inf, so random_level() saturates
+            // to usize::MAX and insert_node tries to allocate that many layers
+            return Err(PyValueError::new_err("m must be at least 2"));
+        }
+        Ok(HnswIndex {
+            vectors: Vec::new(),
+            metadata: Vec::new(),
+            dimension,
+            m,
+            m_max0: m * 2,
+            ef_construction: ef_construction.unwrap_or(200),
+            ml: 1.0 / (m as f64).ln(),
+            entry_point: None,
+            top_level: -1,
+            layers: Vec::new(),
+        })
+    }
+
+    /// Add a vector to the index with optional metadata
+    fn add(&mut self, vector: Vec<f32>, metadata: Option<HashMap<String, String>>) -> PyResult<usize> {
+        if vector.len() != self.dimension {
+            return Err(PyValueError::new_err(format!(
+                "Vector dimension mismatch. Expected {}, got {}",
+                self.dimension,
+                vector.len()
+            )));
+        }
+
+        self.vectors.push(vector);
+        self.metadata.push(metadata.unwrap_or_default());
+        let idx = self.vectors.len() - 1;
+        self.insert_node(idx);
+        Ok(idx)
+    }
+
+    /// Add multiple vectors in batch
+    fn add_batch(
+        &mut self,
+        vectors: Vec<Vec<f32>>,
+        metadata: Option<Vec<HashMap<String, String>>>,
+    ) -> PyResult<Vec<usize>> {
+        for (i, vector) in vectors.iter().enumerate() {
+            if vector.len() != self.dimension {
+                return Err(PyValueError::new_err(format!(
+                    "Vector {} has dimension mismatch. Expected {}, got {}",
+                    i,
+                    self.dimension,
+                    vector.len()
+                )));
+            }
+        }
+
+        let mut indices = Vec::with_capacity(vectors.len());
+        for (i, vector) in vectors.into_iter().enumerate() {
+            self.vectors.push(vector);
+            let meta = metadata
+                .as_ref()
+                .and_then(|m| m.get(i))
+                .cloned()
+                .unwrap_or_default();
+            self.metadata.push(meta);
+            let idx = self.vectors.len() - 1;
+            self.insert_node(idx);
+            indices.push(idx);
+        }
+
+        Ok(indices)
+    }
+
+    /// Approximate k-nearest-neighbor search. `ef` is the query-time beam width
+    /// (must be >= k for reasonable recall)
+    fn search(&self, query: Vec<f32>, k: usize, ef: usize) -> PyResult<(Vec<usize>, Vec<f32>)> {
+        if query.len() != self.dimension {
+            return Err(PyValueError::new_err(format!(
+                "Query dimension mismatch. Expected {}, got {}",
+                self.dimension,
+                query.len()
+            )));
+        }
+        if k == 0 {
+            return Err(PyValueError::new_err("k must be greater than 0"));
+        }
+        if ef == 0 {
+            return Err(PyValueError::new_err("ef must be greater than 0"));
+        }
+
+        let entry = match self.entry_point {
+            Some(e) => e,
+            None => return Ok((Vec::new(), Vec::new())),
+        };
+
+        let current = self.greedy_descend(&query, entry);
+        let mut candidates = self.search_layer_vec(&query, vec![current], ef.max(k), 0);
+        candidates.truncate(k);
+
+        let indices: Vec<usize> = candidates.iter().map(|(idx, _)| *idx).collect();
+        let scores: Vec<f32> = candidates.iter().map(|(_, dist)| 1.0 - dist).collect();
+
+        Ok((indices, scores))
+    }
+
+    /// Approximate k-nearest-neighbor search restricted to vectors whose metadata
+    /// matches `filter_key`/`filter_value`. Filtering happens over the `ef`-sized
+    /// candidate pool, so a very selective filter may return fewer than `k` results;
+    /// raise `ef` to widen the pool.
+    fn search_with_filter(
+        &self,
+        query: Vec<f32>,
+        k: usize,
+        ef: usize,
+        filter_key: String,
+        filter_value: String,
+    ) -> PyResult<(Vec<usize>, Vec<f32>)> {
+        if query.len() != self.dimension {
+            return Err(PyValueError::new_err(format!(
+                "Query dimension mismatch. Expected {}, got {}",
+                self.dimension,
+                query.len()
+            )));
+        }
+        if k == 0 {
+            return Err(PyValueError::new_err("k must be greater than 0"));
+        }
+        if ef == 0 {
+            return Err(PyValueError::new_err("ef must be greater than 0"));
+        }
+
+        let entry = match self.entry_point {
+            Some(e) => e,
+            None => return Ok((Vec::new(), Vec::new())),
+        };
+
+        let current = self.greedy_descend(&query, entry);
+        let candidates = self.search_layer_vec(&query, vec![current], ef.max(k), 0);
+
+        let mut filtered: Vec<(usize, f32)> = candidates
+            .into_iter()
+            .filter(|(idx, _)| {
+                self.metadata
+                    .get(*idx)
+                    .and_then(|m| m.get(&filter_key))
+                    .map(|v| v == &filter_value)
+                    .unwrap_or(false)
+            })
+            .collect();
+        filtered.truncate(k);
+
+        let indices: Vec<usize> = filtered.iter().map(|(idx, _)| *idx).collect();
+        let scores: Vec<f32> = filtered.iter().map(|(_, dist)| 1.0 - dist).collect();
 
         Ok((indices, scores))
     }
+
+    /// Get metadata for a specific index
+    fn get_metadata(&self, index: usize) -> PyResult<HashMap<String, String>> {
+        if index >= self.metadata.len() {
+            return Err(PyValueError::new_err("Index out of bounds"));
+        }
+        Ok(self.metadata[index].clone())
+    }
+
+    /// Get the number of vectors in the index
+    fn size(&self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Clear all vectors, metadata, and graph layers
+    fn clear(&mut self) {
+        self.vectors.clear();
+        self.metadata.clear();
+        self.layers.clear();
+        self.entry_point = None;
+        self.top_level = -1;
+    }
+}
+
+impl HnswIndex {
+    /// Greedily descend from the entry point down to layer 0, following the single
+    /// nearest neighbor at each layer, and return the best entry point found for layer 0
+    fn greedy_descend(&self, query: &[f32], entry: usize) -> usize {
+        let mut current = entry;
+        let mut current_dist = Self::cosine_distance(query, &self.vectors[current]);
+
+        let mut lvl = self.top_level;
+        while lvl > 0 {
+            loop {
+                let mut changed = false;
+                if let Some(neighbors) = self.layers[lvl as usize].get(&current) {
+                    for &n in neighbors {
+                        let d = Self::cosine_distance(query, &self.vectors[n]);
+                        if d < current_dist {
+                            current_dist = d;
+                            current = n;
+                            changed = true;
+                        }
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+            lvl -= 1;
+        }
+
+        current
+    }
 }
 
-/// Brute force k-NN search across all vectors
-/// Returns (indices, distances) for k nearest neighbors
+/// Brute force k-NN search across all vectors using the given metric (defaults to Euclidean)
+/// Returns (indices, scores) for k nearest neighbors
 #[pyfunction]
 fn brute_force_knn(
     query: Vec<f32>,
     vectors: Vec<Vec<f32>>,
     k: usize,
+    metric: Option<MetricArg>,
 ) -> PyResult<(Vec<usize>, Vec<f32>)> {
     if vectors.is_empty() {
         return Ok((Vec::new(), Vec::new()));
     }
 
+    let metric = match metric {
+        Some(m) => Metric::parse(m)?,
+        None => Metric::Euclidean,
+    };
+
     let dimension = query.len();
 
     // Validate all vectors have same dimension
@@ -230,47 +1320,46 @@ fn brute_force_knn(
         }
     }
 
-    // Compute distances in parallel
-    let mut distances: Vec<(usize, f32)> = vectors
+    // Compute scores in parallel
+    let mut scored: Vec<(usize, f32)> = vectors
         .par_iter()
         .enumerate()
-        .map(|(idx, target)| {
-            let dist: f32 = query
-                .iter()
-                .zip(target.iter())
-                .map(|(q, t)| (q - t).powi(2))
-                .sum::<f32>()
-                .sqrt();
-            (idx, dist)
-        })
+        .map(|(idx, target)| (idx, metric.compute(&query, target)))
         .collect();
 
-    // Sort by distance (ascending)
-    distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    metric.sort_best_first(&mut scored);
 
     // Take top-k
-    let k = k.min(distances.len());
-    let indices: Vec<usize> = distances[..k].iter().map(|(idx, _)| *idx).collect();
-    let dists: Vec<f32> = distances[..k].iter().map(|(_, dist)| *dist).collect();
+    let k = k.min(scored.len());
+    let indices: Vec<usize> = scored[..k].iter().map(|(idx, _)| *idx).collect();
+    let scores: Vec<f32> = scored[..k].iter().map(|(_, score)| *score).collect();
 
-    Ok((indices, dists))
+    Ok((indices, scores))
 }
 
-/// Compute radius search - find all vectors within a distance threshold
+/// Find all vectors within a distance threshold (or, for similarity metrics, at or above
+/// a score threshold) using the given metric (defaults to Euclidean)
 #[pyfunction]
 fn radius_search(
     query: Vec<f32>,
     vectors: Vec<Vec<f32>>,
     radius: f32,
+    metric: Option<MetricArg>,
 ) -> PyResult<(Vec<usize>, Vec<f32>)> {
     if vectors.is_empty() {
         return Ok((Vec::new(), Vec::new()));
     }
 
+    let metric = match metric {
+        Some(m) => Metric::parse(m)?,
+        None => Metric::Euclidean,
+    };
+
     let dimension = query.len();
+    let higher_is_better = metric.higher_is_better();
 
-    // Compute distances in parallel and filter by radius
-    let results: Vec<(usize, f32)> = vectors
+    // Compute scores in parallel and filter by the threshold
+    let mut results: Vec<(usize, f32)> = vectors
         .par_iter()
         .enumerate()
         .filter_map(|(idx, target)| {
@@ -278,35 +1367,34 @@ fn radius_search(
                 return None;
             }
 
-            let dist: f32 = query
-                .iter()
-                .zip(target.iter())
-                .map(|(q, t)| (q - t).powi(2))
-                .sum::<f32>()
-                .sqrt();
+            let score = metric.compute(&query, target);
+            let within = if higher_is_better {
+                score >= radius
+            } else {
+                score <= radius
+            };
 
-            if dist <= radius {
-                Some((idx, dist))
+            if within {
+                Some((idx, score))
             } else {
                 None
             }
         })
         .collect();
 
-    // Sort by distance
-    let mut results = results;
-    results.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    metric.sort_best_first(&mut results);
 
     let indices: Vec<usize> = results.iter().map(|(idx, _)| *idx).collect();
-    let distances: Vec<f32> = results.iter().map(|(_, dist)| *dist).collect();
+    let scores: Vec<f32> = results.iter().map(|(_, score)| *score).collect();
 
-    Ok((indices, distances))
+    Ok((indices, scores))
 }
 
 /// Python module definition
 #[pymodule]
 fn vector_search(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<VectorIndex>()?;
+    m.add_class::<HnswIndex>()?;
     m.add_function(wrap_pyfunction!(brute_force_knn, m)?)?;
     m.add_function(wrap_pyfunction!(radius_search, m)?)?;
     Ok(())
@@ -318,7 +1406,7 @@ mod tests {
 
     #[test]
     fn test_vector_index() {
-        let mut index = VectorIndex::new(3);
+        let mut index = VectorIndex::new(3, None, None).unwrap();
 
         // Add vectors
         let _ = index.add(vec![1.0, 2.0, 3.0], None);
@@ -333,6 +1421,182 @@ mod tests {
         assert!(scores[0] > 0.9); // First result should be very similar
     }
 
+    #[test]
+    fn test_vector_index_dot_product_metric() {
+        let mut index = VectorIndex::new(2, Some(MetricArg::Name("dot_product".to_string())), None).unwrap();
+        let _ = index.add(vec![1.0, 0.0], None);
+        let _ = index.add(vec![3.0, 0.0], None);
+
+        let (indices, scores) = index.search(vec![1.0, 0.0], 2).unwrap();
+        assert_eq!(indices[0], 1); // Larger dot product ranks first
+        assert!(scores[0] > scores[1]);
+    }
+
+    #[test]
+    fn test_vector_index_remove_and_compact() {
+        let mut index = VectorIndex::new(2, Some(MetricArg::Name("dot_product".to_string())), None).unwrap();
+        let _ = index.add(vec![1.0, 0.0], None); // idx 0
+        let _ = index.add(vec![3.0, 0.0], None); // idx 1: removed
+        let _ = index.add(vec![5.0, 0.0], None); // idx 2
+
+        index.remove(1).unwrap();
+        assert!(index.remove(99).is_err());
+
+        // Tombstoned entries are skipped by search without shifting surviving indices
+        let (indices, _) = index.search(vec![1.0, 0.0], 3).unwrap();
+        assert_eq!(indices.len(), 2);
+        assert!(!indices.contains(&1));
+        assert_eq!(index.size(), 3); // size() still counts the tombstone until compact()
+
+        let remap = index.compact();
+        assert_eq!(index.size(), 2);
+        assert_eq!(remap.get(&0), Some(&0));
+        assert_eq!(remap.get(&2), Some(&1));
+        assert_eq!(remap.get(&1), None);
+
+        let (indices, _) = index.search(vec![1.0, 0.0], 2).unwrap();
+        assert_eq!(indices.len(), 2);
+    }
+
+    #[test]
+    fn test_vector_index_manhattan_metric_by_code() {
+        let mut index = VectorIndex::new(2, Some(MetricArg::Code(3)), None).unwrap();
+        let _ = index.add(vec![0.0, 0.0], None);
+        let _ = index.add(vec![5.0, 5.0], None);
+
+        let (indices, scores) = index.search(vec![0.0, 0.0], 2).unwrap();
+        assert_eq!(indices[0], 0); // Smaller L1 distance ranks first
+        assert!(scores[0] < scores[1]);
+    }
+
+    #[test]
+    fn test_vector_index_save_load_round_trip() {
+        let mut meta = HashMap::new();
+        meta.insert("label".to_string(), "a".to_string());
+
+        let mut index = VectorIndex::new(3, Some(MetricArg::Name("euclidean".to_string())), None).unwrap();
+        let _ = index.add(vec![1.0, 2.0, 3.0], Some(meta));
+        let _ = index.add(vec![4.0, 5.0, 6.0], None);
+
+        let path = std::env::temp_dir().join("vector_index_test_round_trip.vidx");
+        let path_str = path.to_str().unwrap().to_string();
+
+        index.save(path_str.clone()).unwrap();
+        let loaded = VectorIndex::load(path_str).unwrap();
+
+        assert_eq!(loaded.size(), 2);
+        assert_eq!(loaded.dimension, 3);
+        assert_eq!(loaded.metric, Metric::Euclidean);
+        assert_eq!(loaded.get_metadata(0).unwrap().get("label").unwrap(), "a");
+
+        let (indices, _) = loaded.search(vec![1.0, 2.0, 3.0], 1).unwrap();
+        assert_eq!(indices[0], 0);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_vector_index_save_load_preserves_normalize_flag() {
+        let mut index = VectorIndex::new(
+            2,
+            Some(MetricArg::Name("cosine".to_string())),
+            Some(false),
+        )
+        .unwrap();
+        let _ = index.add(vec![3.0, 4.0], None);
+
+        let path = std::env::temp_dir().join("vector_index_test_normalize_flag.vidx");
+        let path_str = path.to_str().unwrap().to_string();
+
+        index.save(path_str.clone()).unwrap();
+        let loaded = VectorIndex::load(path_str).unwrap();
+
+        assert_eq!(loaded.normalize, false);
+        assert!(loaded.normalized_vectors.is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_vector_index_hybrid_search_rrf() {
+        let mut index = VectorIndex::new(2, None, None).unwrap();
+        let _ = index.add(vec![1.0, 0.0], None); // idx 0
+        let _ = index.add(vec![0.0, 1.0], None); // idx 1
+        let _ = index.add(vec![-1.0, 0.0], None); // idx 2: last by vector and absent from keyword ranking
+
+        // Keyword search ranks idx 1 first, idx 0 second; vector search ranks idx 0 first,
+        // idx 1 second, so each of idx 0 and idx 1 leads one list and trails the other
+        let keyword_ranking = KeywordRanking::Indices(vec![1, 0]);
+        let (indices, scores) = index
+            .hybrid_search(vec![1.0, 0.0], keyword_ranking, 3, None, None, None)
+            .unwrap();
+
+        assert_eq!(indices.len(), 3);
+        assert_eq!(indices[2], 2);
+        assert!(scores[2] < scores[0]);
+        assert!(scores[2] < scores[1]);
+    }
+
+    #[test]
+    fn test_vector_index_hybrid_search_excludes_tombstoned() {
+        let mut index = VectorIndex::new(2, None, None).unwrap();
+        let _ = index.add(vec![1.0, 0.0], None); // idx 0
+        let _ = index.add(vec![0.0, 1.0], None); // idx 1
+        index.remove(1).unwrap();
+
+        // A stale keyword ranking still referencing the removed idx 1 must not leak it
+        // back into the fused results, matching search/search_with_filter/score_quantiles
+        let keyword_ranking = KeywordRanking::Indices(vec![1, 0]);
+        let (indices, _) = index
+            .hybrid_search(vec![1.0, 0.0], keyword_ranking, 3, None, None, None)
+            .unwrap();
+
+        assert_eq!(indices, vec![0]);
+    }
+
+    #[test]
+    fn test_vector_index_hybrid_search_rejects_out_of_bounds_keyword_index() {
+        let mut index = VectorIndex::new(2, None, None).unwrap();
+        let _ = index.add(vec![1.0, 0.0], None); // idx 0
+
+        // A keyword ranking computed before a compact(), or simply malformed, can
+        // reference an index that was never valid; this must error, not panic
+        let keyword_ranking = KeywordRanking::Indices(vec![99]);
+        assert!(index
+            .hybrid_search(vec![1.0, 0.0], keyword_ranking, 1, None, None, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_vector_index_score_quantiles() {
+        let mut index = VectorIndex::new(1, Some(MetricArg::Name("dot_product".to_string())), None).unwrap();
+        for v in 0..100 {
+            let _ = index.add(vec![v as f32], None);
+        }
+
+        let quantiles = index
+            .score_quantiles(vec![1.0], vec![0.0, 0.5, 1.0], Some(0.01))
+            .unwrap();
+
+        // Dot product with [1.0] is just the stored value, so scores range over 0..99
+        assert!(quantiles[0] <= 1.0); // min
+        assert!((quantiles[1] - 49.5).abs() < 5.0); // median, within approximation error
+        assert!(quantiles[2] >= 98.0); // max
+    }
+
+    #[test]
+    fn test_quantile_summary_basic() {
+        let mut summary = QuantileSummary::new(0.01);
+        for v in 1..=100 {
+            summary.update(v as f32);
+        }
+
+        assert_eq!(summary.query(0.0), Some(1.0));
+        assert_eq!(summary.query(1.0), Some(100.0));
+        let median = summary.query(0.5).unwrap();
+        assert!((median - 50.0).abs() < 5.0);
+    }
+
     #[test]
     fn test_brute_force_knn() {
         let query = vec![0.0, 0.0];
@@ -342,8 +1606,87 @@ mod tests {
             vec![5.0, 5.0],
         ];
 
-        let (indices, distances) = brute_force_knn(query, vectors, 2).unwrap();
+        let (indices, distances) = brute_force_knn(query, vectors, 2, None).unwrap();
         assert_eq!(indices.len(), 2);
         assert!(distances[0] < distances[1]); // Sorted by distance
     }
+
+    #[test]
+    fn test_hnsw_index() {
+        let mut index = HnswIndex::new(3, None, None).unwrap();
+
+        for i in 0..50 {
+            let v = i as f32;
+            let _ = index.add(vec![v, v * 2.0, v * 3.0], None);
+        }
+
+        assert_eq!(index.size(), 50);
+
+        let (indices, scores) = index.search(vec![10.0, 20.0, 30.0], 5, 50).unwrap();
+        assert_eq!(indices.len(), 5);
+        assert!(scores[0] > 0.99); // Exact match should be nearly identical
+
+        assert!(index.search(vec![1.0, 2.0, 3.0], 0, 10).is_err());
+        assert!(index.search(vec![1.0, 2.0, 3.0], 1, 0).is_err());
+    }
+
+    #[test]
+    fn test_hnsw_index_rejects_small_m() {
+        assert!(HnswIndex::new(3, Some(1), None).is_err());
+        assert!(HnswIndex::new(3, Some(0), None).is_err());
+        assert!(HnswIndex::new(3, Some(2), None).is_ok());
+    }
+
+    #[test]
+    fn test_hnsw_index_search_with_filter() {
+        let mut index = HnswIndex::new(2, None, None).unwrap();
+        for i in 0..20 {
+            let v = i as f32;
+            let mut meta = HashMap::new();
+            meta.insert("parity".to_string(), if i % 2 == 0 { "even" } else { "odd" }.to_string());
+            let _ = index.add(vec![v, v], Some(meta));
+        }
+
+        let (indices, _) = index
+            .search_with_filter(vec![10.0, 10.0], 3, 20, "parity".to_string(), "even".to_string())
+            .unwrap();
+        assert!(!indices.is_empty());
+        for idx in indices {
+            assert_eq!(index.get_metadata(idx).unwrap().get("parity").unwrap(), "even");
+        }
+    }
+
+    #[test]
+    fn test_hnsw_index_add_batch() {
+        let mut index = HnswIndex::new(2, None, None).unwrap();
+        let vectors = vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![5.0, 6.0]];
+        let indices = index.add_batch(vectors, None).unwrap();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert_eq!(index.size(), 3);
+
+        assert!(index.add_batch(vec![vec![1.0]], None).is_err());
+    }
+
+    #[test]
+    fn test_hnsw_index_get_metadata() {
+        let mut index = HnswIndex::new(2, None, None).unwrap();
+        let mut meta = HashMap::new();
+        meta.insert("label".to_string(), "first".to_string());
+        let idx = index.add(vec![1.0, 2.0], Some(meta)).unwrap();
+
+        assert_eq!(index.get_metadata(idx).unwrap().get("label").unwrap(), "first");
+        assert!(index.get_metadata(idx + 1).is_err());
+    }
+
+    #[test]
+    fn test_hnsw_index_clear() {
+        let mut index = HnswIndex::new(2, None, None).unwrap();
+        let _ = index.add(vec![1.0, 2.0], None);
+        let _ = index.add(vec![3.0, 4.0], None);
+        assert_eq!(index.size(), 2);
+
+        index.clear();
+        assert_eq!(index.size(), 0);
+        assert!(index.search(vec![1.0, 2.0], 1, 10).unwrap().0.is_empty());
+    }
 }